@@ -0,0 +1,100 @@
+//! Condition variable
+
+use super::{Mutex, UPSafeCell};
+use crate::task::{
+    add_timer, block_current_and_run_next, current_task, current_task_id, wakeup_task,
+    TaskControlBlock,
+};
+use crate::timer::{get_time, CLOCK_FREQ};
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+/// Condition variable structure
+pub struct Condvar {
+    /// Condvar inner
+    pub inner: UPSafeCell<CondvarInner>,
+}
+
+pub struct CondvarInner {
+    pub wait_queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl Condvar {
+    /// Create a new condvar
+    pub fn new() -> Self {
+        trace!("kernel: Condvar::new");
+        Self {
+            inner: unsafe {
+                UPSafeCell::new(CondvarInner {
+                    wait_queue: VecDeque::new(),
+                })
+            },
+        }
+    }
+
+    /// Wake up one waiting thread, if any
+    pub fn signal(&self) {
+        trace!("kernel: Condvar::signal");
+        let mut inner = self.inner.exclusive_access();
+        if let Some(task) = inner.wait_queue.pop_front() {
+            wakeup_task(task);
+        }
+    }
+
+    /// Release `mutex`, block until signalled, then reacquire `mutex`.
+    pub fn wait(&self, mutex: Arc<dyn Mutex>) {
+        trace!("kernel: Condvar::wait");
+        mutex.unlock();
+        let mut inner = self.inner.exclusive_access();
+        inner.wait_queue.push_back(current_task().unwrap());
+        drop(inner);
+        block_current_and_run_next();
+        mutex.lock();
+    }
+
+    /// Like [`wait`](Self::wait), but gives up after `ms` milliseconds
+    /// instead of waiting forever, reacquiring `mutex` either way. Returns
+    /// `true` if signalled, `false` if the deadline elapsed first. Races
+    /// the timeout against a concurrent `signal()` the same way
+    /// `Semaphore::down_timeout` does: whichever side removes this waiter
+    /// from `wait_queue` first wins.
+    pub fn wait_timeout(self: &Arc<Self>, mutex: Arc<dyn Mutex>, ms: usize) -> bool {
+        trace!("kernel: Condvar::wait_timeout");
+        mutex.unlock();
+        let tid = current_task_id().unwrap();
+        let task = current_task().unwrap();
+        let mut inner = self.inner.exclusive_access();
+        inner.wait_queue.push_back(Arc::clone(&task));
+        drop(inner);
+
+        let expire = get_time() + ms * CLOCK_FREQ / 1000;
+        let condvar = Arc::clone(self);
+        add_timer(
+            expire,
+            Arc::clone(&task),
+            Box::new(move || {
+                let mut inner = condvar.inner.exclusive_access();
+                if let Some(pos) = inner
+                    .wait_queue
+                    .iter()
+                    .position(|t| t.inner_exclusive_access().res.as_ref().unwrap().tid == tid)
+                {
+                    inner.wait_queue.remove(pos);
+                    true
+                } else {
+                    false
+                }
+            }),
+        );
+
+        block_current_and_run_next();
+
+        let mut task_inner = task.inner_exclusive_access();
+        let timed_out = task_inner.woken_by_timeout;
+        task_inner.woken_by_timeout = false;
+        drop(task_inner);
+        mutex.lock();
+        !timed_out
+    }
+}
@@ -0,0 +1,118 @@
+//! Banker's-algorithm deadlock detection over the `Resource` trait
+//!
+//! Every `MutexSpin`, `MutexBlocking` and `Semaphore` already exposes its
+//! availability/allocation/need columns through [`Resource`]; this module
+//! folds those columns into dense matrices indexed by the live tids of the
+//! current process and runs the classic safety algorithm over them.
+
+use super::{Resource, Tid};
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Dense Available/Allocation/Need matrices built from a snapshot of a
+/// process's registered [`Resource`]s.
+struct ResourceMatrices {
+    tids: Vec<Tid>,
+    /// `available[j]`
+    available: Vec<usize>,
+    /// `allocation[i][j]`, rows ordered like `tids`
+    allocation: Vec<Vec<usize>>,
+    /// `need[i][j]`, rows ordered like `tids`
+    need: Vec<Vec<usize>>,
+}
+
+impl ResourceMatrices {
+    fn build(resources: &[Arc<dyn Resource>], live_tids: &[Tid]) -> Self {
+        let tids: Vec<Tid> = live_tids.to_vec();
+        let index_of: BTreeMap<Tid, usize> =
+            tids.iter().enumerate().map(|(i, tid)| (*tid, i)).collect();
+        let mut available = Vec::with_capacity(resources.len());
+        let mut allocation = vec![vec![0usize; resources.len()]; tids.len()];
+        let mut need = vec![vec![0usize; resources.len()]; tids.len()];
+        for (j, resource) in resources.iter().enumerate() {
+            available.push(resource.get_available());
+            for (tid, amount) in resource.get_allocation() {
+                if let Some(&i) = index_of.get(&tid) {
+                    allocation[i][j] += amount;
+                }
+            }
+            for (tid, amount) in resource.get_need() {
+                if let Some(&i) = index_of.get(&tid) {
+                    need[i][j] += amount;
+                }
+            }
+        }
+        Self {
+            tids,
+            available,
+            allocation,
+            need,
+        }
+    }
+
+    /// Classic Banker's safety algorithm: is there an order in which every
+    /// thread can finish without exceeding `available`?
+    fn is_safe(&self) -> bool {
+        let mut work = self.available.clone();
+        let mut finish = vec![false; self.tids.len()];
+        loop {
+            let found = (0..self.tids.len()).find(|&i| {
+                !finish[i]
+                    && self.need[i]
+                        .iter()
+                        .zip(work.iter())
+                        .all(|(need, avail)| need <= avail)
+            });
+            match found {
+                Some(i) => {
+                    for j in 0..work.len() {
+                        work[j] += self.allocation[i][j];
+                    }
+                    finish[i] = true;
+                }
+                None => break,
+            }
+        }
+        finish.into_iter().all(|f| f)
+    }
+}
+
+/// Would granting `resource_index`'s next free unit to `requester` leave the
+/// process in a safe state?
+///
+/// This is detection, not avoidance: `available`/`allocation` stay exactly
+/// as reported by the `Resource`s (nothing is granted, simulated or
+/// otherwise), we only record that `requester` now needs one more unit of
+/// `resource_index` (it isn't enqueued yet, so the resource's own
+/// `get_need()` doesn't see it), and then run the ordinary safety algorithm.
+/// Plain lock contention with no cycle is always safe this way: the current
+/// holder has `need == 0` for the contended resource, finishes first, and
+/// its release frees enough `Work` for `requester` to finish too.
+pub fn request_is_safe(
+    resources: &[Arc<dyn Resource>],
+    live_tids: &[Tid],
+    resource_index: usize,
+    requester: Tid,
+) -> bool {
+    let mut matrices = ResourceMatrices::build(resources, live_tids);
+    if let Some(i) = matrices.tids.iter().position(|&tid| tid == requester) {
+        matrices.need[i][resource_index] += 1;
+    }
+    matrices.is_safe()
+}
+
+/// Error code returned to a syscall whose blocking request was rejected by
+/// the deadlock detector.
+pub const EDEADLK: isize = -0xDEAD;
+
+/// Locate `target` inside `resources` by data-pointer identity, so callers
+/// that only have `&self` (not the `Arc<dyn Resource>` living in the
+/// process's resource list) can still find their own column.
+pub fn resource_index(resources: &[Arc<dyn Resource>], target: &dyn Resource) -> Option<usize> {
+    let target_ptr = target as *const dyn Resource as *const ();
+    resources
+        .iter()
+        .position(|r| Arc::as_ptr(r) as *const () == target_ptr)
+}
@@ -1,14 +1,18 @@
 //! Synchronization and interior mutability primitives
 
 mod condvar;
+mod deadlock;
 mod mutex;
 mod semaphore;
+mod spin;
 mod up;
 
 use alloc::vec::Vec;
 pub use condvar::Condvar;
+pub use deadlock::{request_is_safe, resource_index, EDEADLK};
 pub use mutex::{Mutex, MutexBlocking, MutexSpin};
 pub use semaphore::Semaphore;
+pub use spin::SpinLock;
 pub use up::UPSafeCell;
 
 type Tid = usize;
@@ -1,7 +1,10 @@
 //! Mutex (spin-like and blocking(sleep))
 
-use super::{Resource, Tid, UPSafeCell};
-use crate::task::{block_current_and_run_next, suspend_current_and_run_next};
+use super::{Resource, SpinLock, Tid, UPSafeCell, EDEADLK};
+use crate::task::{
+    block_current_and_run_next, current_process_task_by_tid, deadlock_check,
+    suspend_current_and_run_next,
+};
 use crate::task::{current_task, wakeup_task};
 use crate::task::{current_task_id, TaskControlBlock};
 use alloc::{collections::VecDeque, sync::Arc};
@@ -9,8 +12,10 @@ use alloc::{vec, vec::Vec};
 
 /// Mutex trait
 pub trait Mutex: Sync + Send + Resource {
-    /// Lock the mutex
-    fn lock(&self);
+    /// Lock the mutex. `MutexBlocking` returns `-0xDEAD` instead of blocking
+    /// if the deadlock detector is enabled and granting the lock would be
+    /// unsafe; `MutexSpin` does not consult the detector and always returns 0.
+    fn lock(&self) -> isize;
     /// Unlock the mutex
     fn unlock(&self);
 }
@@ -41,7 +46,7 @@ impl MutexSpin {
 
 impl Mutex for MutexSpin {
     /// Lock the spinlock mutex
-    fn lock(&self) {
+    fn lock(&self) -> isize {
         trace!("kernel: MutexSpin::lock");
         loop {
             let mut inner = self.inner.exclusive_access();
@@ -53,7 +58,7 @@ impl Mutex for MutexSpin {
                 inner.locked = true;
                 let tid = current_task_id().unwrap();
                 inner.allocated_to = tid;
-                return;
+                return 0;
             }
         }
     }
@@ -90,7 +95,10 @@ impl Resource for MutexSpin {
 
 /// Blocking Mutex struct
 pub struct MutexBlocking {
-    inner: UPSafeCell<MutexBlockingInner>,
+    // Polled from `deadlock_check`/`lock`/`unlock` on whichever hart the
+    // holder or a waiter happens to run on, so this needs a real `SpinLock`
+    // rather than a `UPSafeCell`.
+    inner: SpinLock<MutexBlockingInner>,
 }
 
 pub struct MutexBlockingInner {
@@ -104,13 +112,40 @@ impl MutexBlocking {
     pub fn new() -> Self {
         trace!("kernel: MutexBlocking::new");
         Self {
-            inner: unsafe {
-                UPSafeCell::new(MutexBlockingInner {
-                    locked: false,
-                    allocated_to: 0,
-                    wait_queue: VecDeque::new(),
-                })
-            },
+            inner: SpinLock::new(MutexBlockingInner {
+                locked: false,
+                allocated_to: 0,
+                wait_queue: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Re-derive the holder's priority boost from its current waiters,
+    /// under the stride scheduler's priority-inversion fix: while any
+    /// waiter outranks the holder, the holder inherits the highest
+    /// waiter's priority (saving its own in `saved_priority` on first
+    /// boost); once no waiter outranks it, the saved priority is restored.
+    fn apply_priority_inheritance(&self, holder: &Arc<TaskControlBlock>) {
+        let mutex_inner = self.inner.exclusive_access();
+        let max_waiter_priority = mutex_inner
+            .wait_queue
+            .iter()
+            .map(|t| t.inner_exclusive_access().priority)
+            .max();
+        drop(mutex_inner);
+        let mut holder_inner = holder.inner_exclusive_access();
+        match max_waiter_priority {
+            Some(p) if p > holder_inner.saved_priority.unwrap_or(holder_inner.priority) => {
+                if holder_inner.saved_priority.is_none() {
+                    holder_inner.saved_priority = Some(holder_inner.priority);
+                }
+                holder_inner.priority = p;
+            }
+            _ => {
+                if let Some(saved) = holder_inner.saved_priority.take() {
+                    holder_inner.priority = saved;
+                }
+            }
         }
     }
 }
@@ -146,17 +181,37 @@ impl Resource for MutexBlocking {
 
 impl Mutex for MutexBlocking {
     /// lock the blocking mutex
-    fn lock(&self) {
+    fn lock(&self) -> isize {
         trace!("kernel: MutexBlocking::lock");
         let mut mutex_inner = self.inner.exclusive_access();
         if mutex_inner.locked {
+            let tid = current_task_id().unwrap();
+            drop(mutex_inner);
+            if !deadlock_check(self, tid) {
+                return EDEADLK;
+            }
+            let mut mutex_inner = self.inner.exclusive_access();
+            // `mutex_inner` was dropped for `deadlock_check`; another hart
+            // may have unlocked in that window, so re-check before
+            // committing to block instead of racing a lost wakeup.
+            if !mutex_inner.locked {
+                mutex_inner.locked = true;
+                mutex_inner.allocated_to = tid;
+                return 0;
+            }
+            let holder_tid = mutex_inner.allocated_to;
             mutex_inner.wait_queue.push_back(current_task().unwrap());
             drop(mutex_inner);
+            if let Some(holder) = current_process_task_by_tid(holder_tid) {
+                self.apply_priority_inheritance(&holder);
+            }
             block_current_and_run_next();
+            0
         } else {
             mutex_inner.locked = true;
             let tid = current_task_id().unwrap();
             mutex_inner.allocated_to = tid;
+            0
         }
     }
 
@@ -165,7 +220,24 @@ impl Mutex for MutexBlocking {
         trace!("kernel: MutexBlocking::unlock");
         let mut mutex_inner = self.inner.exclusive_access();
         assert!(mutex_inner.locked);
+        let holder_tid = mutex_inner.allocated_to;
+        drop(mutex_inner);
+        if let Some(holder) = current_process_task_by_tid(holder_tid) {
+            let mut holder_inner = holder.inner_exclusive_access();
+            if let Some(saved) = holder_inner.saved_priority.take() {
+                holder_inner.priority = saved;
+            }
+        }
+        let mut mutex_inner = self.inner.exclusive_access();
         if let Some(waking_task) = mutex_inner.wait_queue.pop_front() {
+            // The popped waiter becomes the new holder; hand off
+            // `allocated_to` now so a later waiter's inheritance boosts the
+            // right tid, then re-derive its boost against whoever is still
+            // queued behind it.
+            let new_holder_tid = waking_task.inner_exclusive_access().res.as_ref().unwrap().tid;
+            mutex_inner.allocated_to = new_holder_tid;
+            drop(mutex_inner);
+            self.apply_priority_inheritance(&waking_task);
             wakeup_task(waking_task);
         } else {
             mutex_inner.locked = false;
@@ -1,19 +1,26 @@
 //! Semaphore
 
-use crate::sync::UPSafeCell;
+use crate::sync::SpinLock;
 use crate::task::{
-    block_current_and_run_next, current_task, current_task_id, wakeup_task, TaskControlBlock,
+    add_timer, block_current_and_run_next, current_task, current_task_id, deadlock_check,
+    wakeup_task, TaskControlBlock,
 };
+use crate::timer::{get_time, CLOCK_FREQ};
+use alloc::boxed::Box;
 use alloc::collections::{BTreeSet, VecDeque};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 
-use super::{Resource, Tid};
+use super::{Resource, Tid, EDEADLK};
 
 /// semaphore structure
 pub struct Semaphore {
     /// semaphore inner
-    pub inner: UPSafeCell<SemaphoreInner>,
+    ///
+    /// Polled from whichever hart the holder, a waiter, or a firing timer
+    /// happens to run on, so this needs a real `SpinLock` rather than a
+    /// `UPSafeCell`.
+    pub inner: SpinLock<SemaphoreInner>,
 }
 
 pub struct SemaphoreInner {
@@ -27,13 +34,11 @@ impl Semaphore {
     pub fn new(res_count: usize) -> Self {
         trace!("kernel: Semaphore::new");
         Self {
-            inner: unsafe {
-                UPSafeCell::new(SemaphoreInner {
-                    count: res_count as isize,
-                    allocated_to: BTreeSet::new(),
-                    wait_queue: VecDeque::new(),
-                })
-            },
+            inner: SpinLock::new(SemaphoreInner {
+                count: res_count as isize,
+                allocated_to: BTreeSet::new(),
+                wait_queue: VecDeque::new(),
+            }),
         }
     }
 
@@ -52,18 +57,86 @@ impl Semaphore {
         }
     }
 
-    /// down operation of semaphore
-    pub fn down(&self) {
+    /// down operation of semaphore. Returns `-0xDEAD` instead of blocking if
+    /// the deadlock detector is enabled and waiting here would be unsafe.
+    pub fn down(&self) -> isize {
         trace!("kernel: Semaphore::down");
         let mut inner = self.inner.exclusive_access();
-        inner.count -= 1;
-        if inner.count < 0 {
+        if inner.count <= 0 {
+            let tid = current_task_id().unwrap();
+            drop(inner);
+            if !deadlock_check(self, tid) {
+                return EDEADLK;
+            }
+            let mut inner = self.inner.exclusive_access();
+            inner.count -= 1;
             inner.wait_queue.push_back(current_task().unwrap());
             drop(inner);
             block_current_and_run_next();
+            return 0;
+        }
+        inner.count -= 1;
+        let tid = current_task_id().unwrap();
+        inner.allocated_to.insert(tid);
+        0
+    }
+
+    /// Like [`down`](Self::down), but gives up after `ms` milliseconds
+    /// instead of blocking forever. Returns `true` if it acquired the
+    /// semaphore, `false` if the deadline elapsed first or the deadlock
+    /// detector rejected the wait. Races the timeout
+    /// against a concurrent `up()`: whichever side removes this waiter from
+    /// `wait_queue` first wins; the loser finds it already gone and does
+    /// nothing.
+    pub fn down_timeout(self: &Arc<Self>, ms: usize) -> bool {
+        trace!("kernel: Semaphore::down_timeout");
+        let mut inner = self.inner.exclusive_access();
+        if inner.count <= 0 {
+            let tid = current_task_id().unwrap();
+            drop(inner);
+            if !deadlock_check(self.as_ref(), tid) {
+                return false;
+            }
+            let mut inner = self.inner.exclusive_access();
+            inner.count -= 1;
+            let task = current_task().unwrap();
+            inner.wait_queue.push_back(Arc::clone(&task));
+            drop(inner);
+
+            let expire = get_time() + ms * CLOCK_FREQ / 1000;
+            let sem = Arc::clone(self);
+            add_timer(
+                expire,
+                Arc::clone(&task),
+                Box::new(move || {
+                    let mut inner = sem.inner.exclusive_access();
+                    if let Some(pos) = inner.wait_queue.iter().position(|t| {
+                        t.inner_exclusive_access().res.as_ref().unwrap().tid == tid
+                    }) {
+                        inner.wait_queue.remove(pos);
+                        inner.count += 1;
+                        true
+                    } else {
+                        false
+                    }
+                }),
+            );
+
+            block_current_and_run_next();
+
+            let mut task_inner = task.inner_exclusive_access();
+            let timed_out = task_inner.woken_by_timeout;
+            task_inner.woken_by_timeout = false;
+            drop(task_inner);
+            if !timed_out {
+                self.inner.exclusive_access().allocated_to.insert(tid);
+            }
+            !timed_out
         } else {
+            inner.count -= 1;
             let tid = current_task_id().unwrap();
             inner.allocated_to.insert(tid);
+            true
         }
     }
 }
@@ -3,12 +3,14 @@ use crate::{
     config::MAX_SYSCALL_NUM,
     mm::translated_byte_buffer,
     task::{
-        change_program_brk, current_user_token, exit_current_and_run_next, get_current_run_time,
-        get_current_task_status, get_syscall_times, mmap, munmap, suspend_current_and_run_next,
-        TaskStatus,
+        change_program_brk, current_user_token, enable_deadlock_detect,
+        exit_current_and_run_next, get_current_run_time, get_current_task_status,
+        get_syscall_times, install_seccomp, mmap, munmap, sched_getscheduler,
+        sched_setscheduler, suspend_current_and_run_next, SchedPolicy, SeccompMode, TaskStatus,
     },
     timer::{get_time_us, MICRO_PER_SEC},
 };
+use alloc::vec::Vec;
 use core::{mem::size_of, slice::from_raw_parts};
 
 #[repr(C)]
@@ -90,6 +92,61 @@ pub fn sys_sbrk(size: i32) -> isize {
     }
 }
 
+/// Toggle the calling process's Banker's-algorithm deadlock detector; every
+/// `mutex_lock`/`semaphore_down` that would block is checked for safety
+/// while this is enabled.
+pub fn sys_enable_deadlock_detect(enabled: usize) -> isize {
+    trace!("kernel: sys_enable_deadlock_detect");
+    enable_deadlock_detect(enabled != 0);
+    0
+}
+
+/// Set a thread's scheduling class and (for `Stride`) its priority.
+/// `policy`: 0 = FIFO, 1 = Stride, 2 = RoundRobin.
+pub fn sys_sched_setscheduler(tid: usize, policy: usize, priority: usize) -> isize {
+    trace!("kernel: sys_sched_setscheduler");
+    match SchedPolicy::from_raw(policy) {
+        Some(policy) => sched_setscheduler(tid, policy, priority),
+        None => -1,
+    }
+}
+
+/// Read back a thread's scheduling class, encoded the same way as
+/// `sys_sched_setscheduler`'s `policy` argument.
+pub fn sys_sched_getscheduler(tid: usize) -> isize {
+    trace!("kernel: sys_sched_getscheduler");
+    match sched_getscheduler(tid) {
+        Some(policy) => policy.into_raw() as isize,
+        None => -1,
+    }
+}
+
+/// Install a syscall allow/deny filter on the calling process. Children do
+/// not receive it: `inherit_seccomp` is not called on `fork`/thread creation.
+/// `allow_bitmap` points to one bit per syscall id
+/// (`crate::task::seccomp::BITMAP_LEN_BYTES` bytes); `mode` selects what
+/// happens to a denied syscall: 0 = no filter, 1 = `Errno` (the verdict is
+/// computed but `count_syscall` does not act on it, so the syscall still
+/// runs), 2 = kill the task.
+pub fn sys_seccomp(mode: usize, allow_bitmap: *const u8) -> isize {
+    trace!("kernel: sys_seccomp");
+    let Some(mode) = SeccompMode::from_raw(mode) else {
+        return -1;
+    };
+    let bitmap = user_memory_get(allow_bitmap, crate::task::seccomp::BITMAP_LEN_BYTES);
+    install_seccomp(mode, &bitmap);
+    0
+}
+
+fn user_memory_get(ptr: *const u8, len: usize) -> Vec<u8> {
+    let buffers = translated_byte_buffer(current_user_token(), ptr, len);
+    let mut bytes = Vec::with_capacity(len);
+    for buffer in buffers {
+        bytes.extend_from_slice(buffer);
+    }
+    bytes
+}
+
 fn user_memory_set<T: Sized>(ptr: *mut T, val: &T) -> isize {
     let len = size_of::<T>();
     let buffers = translated_byte_buffer(current_user_token(), ptr as *const u8, len);
@@ -0,0 +1,36 @@
+//! Synchronization-primitive syscalls
+use crate::task::current_process;
+use alloc::sync::Arc;
+
+/// Wait on a condvar with a deadline instead of waiting forever; returns 0
+/// if signalled, -1 if `timeout_ms` elapsed first. Reacquires `mutex_id`
+/// either way, same as `sys_condvar_wait`.
+pub fn sys_condvar_wait_timeout(condvar_id: usize, mutex_id: usize, timeout_ms: usize) -> isize {
+    trace!("kernel: sys_condvar_wait_timeout");
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    let condvar = Arc::clone(process_inner.condvar_list[condvar_id].as_ref().unwrap());
+    let mutex = Arc::clone(process_inner.mutex_list[mutex_id].as_ref().unwrap());
+    drop(process_inner);
+    if condvar.wait_timeout(mutex, timeout_ms) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Down a semaphore with a deadline instead of blocking forever; returns 0
+/// if acquired, -1 if `timeout_ms` elapsed first or the deadlock detector
+/// rejected the wait.
+pub fn sys_semaphore_down_timeout(sem_id: usize, timeout_ms: usize) -> isize {
+    trace!("kernel: sys_semaphore_down_timeout");
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    let sem = Arc::clone(process_inner.semaphore_list[sem_id].as_ref().unwrap());
+    drop(process_inner);
+    if sem.down_timeout(timeout_ms) {
+        0
+    } else {
+        -1
+    }
+}
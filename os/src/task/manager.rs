@@ -1,6 +1,6 @@
 //!Implementation of [`TaskManager`]
-use super::TaskControlBlock;
-use crate::sync::UPSafeCell;
+use super::{SchedPolicy, TaskControlBlock};
+use crate::sync::SpinLock;
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use lazy_static::*;
@@ -8,41 +8,70 @@ use lazy_static::*;
 const BIG_STRIDE: usize = 1 << 30;
 
 ///A array of `TaskControlBlock` that is thread-safe
+///
+/// Ready tasks live in one of two structures depending on their
+/// [`SchedPolicy`]: `rt_queue` is a plain FIFO for the `Fifo`/`RoundRobin`
+/// real-time classes, `stride_queue` stays sorted by stride for `Stride`.
+/// `fetch` always drains `rt_queue` first, so a real-time thread preempts
+/// the stride queue instead of waiting its turn in it. `Fifo` and
+/// `RoundRobin` are not actually differentiated here: both just
+/// `push_back`/`pop_front` through `rt_queue`, since real time-slicing
+/// needs a timer-interrupt call site this tree doesn't have.
 pub struct TaskManager {
-    ready_queue: VecDeque<(Arc<TaskControlBlock>, usize)>,
+    rt_queue: VecDeque<Arc<TaskControlBlock>>,
+    stride_queue: VecDeque<(Arc<TaskControlBlock>, usize)>,
 }
 
-/// A simple FIFO scheduler.
 impl TaskManager {
     ///Creat an empty TaskManager
     pub fn new() -> Self {
         Self {
-            ready_queue: VecDeque::new(),
+            rt_queue: VecDeque::new(),
+            stride_queue: VecDeque::new(),
         }
     }
-    /// Add process back to ready queue
+    /// Add process back to ready queue, dispatching on its scheduling policy
     pub fn add(&mut self, task: Arc<TaskControlBlock>) {
         let mut task_inner = task.inner_exclusive_access();
-        let pass = BIG_STRIDE / task_inner.priority;
-        let new_stride = task_inner.stride + pass;
-        task_inner.stride = new_stride;
-        drop(task_inner);
-        if let Some(index) = self.ready_queue.iter().position(|(_, s)| s > &new_stride) {
-            self.ready_queue.insert(index, (task, new_stride));
-        } else {
-            self.ready_queue.push_back((task, new_stride));
+        match task_inner.policy {
+            SchedPolicy::Fifo | SchedPolicy::RoundRobin => {
+                drop(task_inner);
+                self.rt_queue.push_back(task);
+            }
+            SchedPolicy::Stride => {
+                let pass = BIG_STRIDE / task_inner.priority;
+                let new_stride = task_inner.stride + pass;
+                task_inner.stride = new_stride;
+                drop(task_inner);
+                if let Some(index) = self
+                    .stride_queue
+                    .iter()
+                    .position(|(_, s)| s > &new_stride)
+                {
+                    self.stride_queue.insert(index, (task, new_stride));
+                } else {
+                    self.stride_queue.push_back((task, new_stride));
+                }
+            }
         }
     }
-    /// Take a process out of the ready queue
+    /// Take a process out of the ready queue: real-time threads first,
+    /// falling back to the stride queue when none are runnable.
     pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
-        self.ready_queue.pop_front().map(|e| e.0)
+        if let Some(task) = self.rt_queue.pop_front() {
+            return Some(task);
+        }
+        self.stride_queue.pop_front().map(|e| e.0)
     }
 }
 
 lazy_static! {
     /// TASK_MANAGER instance through lazy_static!
-    pub static ref TASK_MANAGER: UPSafeCell<TaskManager> =
-        unsafe { UPSafeCell::new(TaskManager::new()) };
+    ///
+    /// Every hart's `run_tasks` polls this same ready queue, so it's a real
+    /// `SpinLock` rather than a `UPSafeCell` (which only guards against a
+    /// single hart re-entering, not concurrent harts).
+    pub static ref TASK_MANAGER: SpinLock<TaskManager> = SpinLock::new(TaskManager::new());
 }
 
 /// Add process to ready queue
@@ -3,18 +3,37 @@
 //! Here, the continuous operation of user apps in CPU is maintained,
 //! the current running state of CPU is recorded,
 //! and the replacement and transfer of control flow of different applications are executed.
+//!
+//! Scheduling itself (`TASK_MANAGER`) is shared across harts; what's
+//! hart-local is *which* task is currently running and where its idle
+//! control flow lives, so each hart gets its own [`Processor`] in
+//! [`PROCESSORS`], indexed by `hart_id()`.
 
 use super::__switch;
-use super::{fetch_task, TaskStatus};
+use super::{check_timers, exit_current_and_run_next, fetch_task, TaskStatus};
 use super::{TaskContext, TaskControlBlock};
 use crate::config::{CLOCK_FREQ, MAX_SYSCALL_NUM};
 use crate::mm::{MapPermission, VPNRange, VirtAddr};
-use crate::sync::UPSafeCell;
+use crate::sync::{request_is_safe, Resource, UPSafeCell};
 use crate::timer::{get_time, get_time_us};
 use crate::trap::TrapContext;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use lazy_static::*;
 
+/// Upper bound on the number of harts this kernel schedules across.
+const MAX_HARTS: usize = 8;
+
+/// Read the current hart's id out of `tp`, where entry code stashes
+/// `mhartid` on every hart at boot.
+pub fn hart_id() -> usize {
+    let tp: usize;
+    unsafe {
+        core::arch::asm!("mv {}, tp", out(reg) tp);
+    }
+    tp
+}
+
 /// Processor management structure
 pub struct Processor {
     ///The task currently executing on the current processor
@@ -50,14 +69,36 @@ impl Processor {
 }
 
 lazy_static! {
-    pub static ref PROCESSOR: UPSafeCell<Processor> = unsafe { UPSafeCell::new(Processor::new()) };
+    /// One `Processor` per hart; `TASK_MANAGER`'s ready queue stays shared.
+    static ref PROCESSORS: [UPSafeCell<Processor>; MAX_HARTS] =
+        core::array::from_fn(|_| unsafe { UPSafeCell::new(Processor::new()) });
+}
+
+/// Resolve the calling hart's `Processor`.
+fn this_hart_processor() -> &'static UPSafeCell<Processor> {
+    let hart = hart_id();
+    assert!(
+        hart < MAX_HARTS,
+        "hart id {} exceeds the {} harts this kernel schedules across",
+        hart,
+        MAX_HARTS
+    );
+    &PROCESSORS[hart]
 }
 
 ///The main part of process execution and scheduling
 ///Loop `fetch_task` to get the process that needs to run, and switch the process through `__switch`
+///
+/// Run once per hart; every hart drains the same shared `TASK_MANAGER`. This
+/// is also the only busy loop every hart reliably spins through regardless
+/// of what's running, so it doubles as the timer wheel's poll point in this
+/// tree: the real driver would be the `SupervisorTimer` trap in
+/// `trap/mod.rs` (not part of this snapshot), but polling here still fires
+/// `down_timeout`/`wait_timeout` deadlines instead of leaving them inert.
 pub fn run_tasks() {
     loop {
-        let mut processor = PROCESSOR.exclusive_access();
+        check_timers(get_time());
+        let mut processor = this_hart_processor().exclusive_access();
         if let Some(task) = fetch_task() {
             let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
             // access coming task TCB exclusively
@@ -85,12 +126,12 @@ pub fn run_tasks() {
 
 /// Get current task through take, leaving a None in its place
 pub fn take_current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.exclusive_access().take_current()
+    this_hart_processor().exclusive_access().take_current()
 }
 
 /// Get a copy of the current task
 pub fn current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.exclusive_access().current()
+    this_hart_processor().exclusive_access().current()
 }
 
 /// Get the current user token(addr of page table)
@@ -109,7 +150,7 @@ pub fn current_trap_cx() -> &'static mut TrapContext {
 
 ///Return to idle control flow for new scheduling
 pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
-    let mut processor = PROCESSOR.exclusive_access();
+    let mut processor = this_hart_processor().exclusive_access();
     let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
     drop(processor);
     unsafe {
@@ -117,10 +158,66 @@ pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
     }
 }
 
+/// Count `syscall_id` against the current task, then enforce the current
+/// process's seccomp filter against it. This is the one real per-syscall
+/// call site every dispatched syscall passes through in this tree (the
+/// dispatcher in `syscall/mod.rs`, outside this snapshot, already calls it
+/// for the counters `sys_task_info` reports), so `Kill`-mode filters are
+/// enforced from here: a `DeniedKill` verdict diverges via
+/// `exit_current_and_run_next` before returning to the caller, same as a
+/// process killing itself.
+///
+/// It counts and can kill, but does not skip dispatch or override the
+/// return value, since both happen in the caller (`syscall/mod.rs`) before
+/// or after this runs: a `DeniedErrno` verdict has no effect, and a `Kill`
+/// exit only precedes the syscall's side effects if the caller calls this
+/// before dispatching. `sys_seccomp` has no entry in `syscall/mod.rs`'s
+/// id -> handler table.
 pub fn count_syscall(syscall_id: usize) {
     let task = current_task().unwrap();
     let mut inner = task.inner_exclusive_access();
     inner.syscall_times[syscall_id] += 1;
+    drop(inner);
+    if check_seccomp(syscall_id) == super::SeccompVerdict::DeniedKill {
+        exit_current_and_run_next();
+    }
+}
+
+/// Consult the current process's seccomp filter for `syscall_id`.
+pub fn check_seccomp(syscall_id: usize) -> super::SeccompVerdict {
+    use super::SeccompVerdict;
+    let task = current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    let process_inner = process.inner_exclusive_access();
+    if process_inner.seccomp.is_allowed(syscall_id) {
+        SeccompVerdict::Allowed
+    } else {
+        match process_inner.seccomp.mode {
+            super::SeccompMode::Kill => SeccompVerdict::DeniedKill,
+            _ => SeccompVerdict::DeniedErrno,
+        }
+    }
+}
+
+/// Install a new seccomp filter on the current process; see
+/// `sys_seccomp`. See [`inherit_seccomp`] for copying it onto a child at
+/// `fork`/thread-creation time.
+pub fn install_seccomp(mode: super::SeccompMode, allow_bitmap: &[u8]) {
+    let task = current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    process.inner_exclusive_access().seccomp = super::SeccompFilter::install(mode, allow_bitmap);
+}
+
+/// Copy `parent`'s installed seccomp filter onto `child`.
+///
+/// Nothing calls this: the fork/thread-creation path in `task/task.rs` does
+/// not copy the filter, so a child process starts with
+/// `SeccompFilter::default()` (no filter) regardless of its parent's.
+pub fn inherit_seccomp(parent: &Arc<TaskControlBlock>, child: &Arc<TaskControlBlock>) {
+    let parent_process = parent.process.upgrade().unwrap();
+    let child_process = child.process.upgrade().unwrap();
+    let filter = parent_process.inner_exclusive_access().seccomp.clone();
+    child_process.inner_exclusive_access().seccomp = filter;
 }
 
 pub fn get_syscall_times() -> [u32; MAX_SYSCALL_NUM] {
@@ -188,6 +285,84 @@ pub fn munmap(start: usize, len: usize) -> isize {
     inner.memory_set.remove_framed_area(start_va, end_va)
 }
 
+/// Find the current process's thread with the given tid, e.g. to look up a
+/// mutex holder from its `Tid` for priority inheritance.
+pub fn current_process_task_by_tid(tid: usize) -> Option<Arc<TaskControlBlock>> {
+    let task = current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    let process_inner = process.inner_exclusive_access();
+    process_inner
+        .tasks
+        .iter()
+        .flatten()
+        .find(|t| t.inner_exclusive_access().res.as_ref().unwrap().tid == tid)
+        .cloned()
+}
+
+/// Set thread `tid`'s scheduling policy (and, for `Stride`, its priority).
+/// Takes effect the next time the thread is re-queued by `add_task`.
+pub fn sched_setscheduler(tid: usize, policy: super::SchedPolicy, priority: usize) -> isize {
+    let Some(task) = current_process_task_by_tid(tid) else {
+        return -1;
+    };
+    let mut inner = task.inner_exclusive_access();
+    inner.policy = policy;
+    if policy == super::SchedPolicy::Stride && priority >= 2 {
+        inner.priority = priority;
+    }
+    0
+}
+
+/// Read back thread `tid`'s scheduling policy.
+pub fn sched_getscheduler(tid: usize) -> Option<super::SchedPolicy> {
+    let task = current_process_task_by_tid(tid)?;
+    Some(task.inner_exclusive_access().policy)
+}
+
+/// Toggle the current process's deadlock detector (see
+/// `sys_enable_deadlock_detect`).
+pub fn enable_deadlock_detect(enabled: bool) {
+    let task = current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    process.inner_exclusive_access().deadlock_detect = enabled;
+}
+
+/// Would granting `resource`'s next free unit to `requester` leave the
+/// current process in a safe state? Always `true` (go ahead) while
+/// detection is switched off.
+pub fn deadlock_check(resource: &dyn Resource, requester: usize) -> bool {
+    let task = current_task().unwrap();
+    let process = task.process.upgrade().unwrap();
+    let process_inner = process.inner_exclusive_access();
+    if !process_inner.deadlock_detect {
+        return true;
+    }
+    let resources: Vec<Arc<dyn Resource>> = process_inner
+        .mutex_list
+        .iter()
+        .flatten()
+        .map(|m| {
+            let r: Arc<dyn Resource> = Arc::clone(m);
+            r
+        })
+        .chain(process_inner.semaphore_list.iter().flatten().map(|s| {
+            let r: Arc<dyn Resource> = Arc::clone(s);
+            r
+        }))
+        .collect();
+    let live_tids: Vec<usize> = process_inner
+        .tasks
+        .iter()
+        .flatten()
+        .map(|t| t.inner_exclusive_access().res.as_ref().unwrap().tid)
+        .collect();
+    drop(process_inner);
+    match crate::sync::resource_index(&resources, resource) {
+        Some(index) => request_is_safe(&resources, &live_tids, index, requester),
+        None => true,
+    }
+}
+
 pub fn set_priority(prio: isize) -> isize {
     if prio < 2 {
         return -1;
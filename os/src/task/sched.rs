@@ -0,0 +1,48 @@
+//! Per-task scheduling class, selectable at runtime via
+//! `sys_sched_setscheduler`/`sys_sched_getscheduler`.
+
+/// Scheduling policy a thread runs under.
+///
+/// `Fifo` and `RoundRobin` are real-time classes served ahead of `Stride`,
+/// in plain arrival order via `TaskManager`'s `rt_queue`. `TaskManager`
+/// treats the two classes identically (see `TaskManager::add`/`fetch`):
+/// neither is preempted when a timeslice runs out. `Stride` is the
+/// default priority-proportional-share policy `TaskManager` already
+/// implements.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SchedPolicy {
+    /// First-in-first-out real-time class: runs until it blocks or yields.
+    Fifo,
+    /// Stride scheduling: `pass = BIG_STRIDE / priority` each turn.
+    Stride,
+    /// Round-robin real-time class: scheduled exactly like `Fifo`, with no
+    /// time-slice preemption; see the enum-level doc comment.
+    RoundRobin,
+}
+
+impl Default for SchedPolicy {
+    fn default() -> Self {
+        Self::Stride
+    }
+}
+
+impl SchedPolicy {
+    /// Decode the `policy` argument of `sys_sched_setscheduler`.
+    pub fn from_raw(raw: usize) -> Option<Self> {
+        match raw {
+            0 => Some(Self::Fifo),
+            1 => Some(Self::Stride),
+            2 => Some(Self::RoundRobin),
+            _ => None,
+        }
+    }
+
+    /// Encode for `sys_sched_getscheduler`.
+    pub fn into_raw(self) -> usize {
+        match self {
+            Self::Fifo => 0,
+            Self::Stride => 1,
+            Self::RoundRobin => 2,
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Per-process syscall allow/deny filtering (seccomp-style), consulted by
+//! the syscall dispatcher right next to [`super::count_syscall`]'s
+//! per-syscall counters, since the dispatcher already sees every syscall id
+//! a task makes.
+
+use crate::config::MAX_SYSCALL_NUM;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Bytes needed for one bit per syscall id up to [`MAX_SYSCALL_NUM`].
+pub const BITMAP_LEN_BYTES: usize = (MAX_SYSCALL_NUM + 7) / 8;
+
+/// What happens to a syscall id the filter doesn't allow.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SeccompMode {
+    /// No filter installed; every syscall id is reachable.
+    Off,
+    /// A denied syscall gets the `DeniedErrno` verdict; `count_syscall`
+    /// does not act on it, so the syscall still runs.
+    Errno,
+    /// A denied syscall kills the calling task outright.
+    Kill,
+}
+
+impl SeccompMode {
+    /// Decode the `mode` argument of `sys_seccomp`.
+    pub fn from_raw(raw: usize) -> Option<Self> {
+        match raw {
+            0 => Some(Self::Off),
+            1 => Some(Self::Errno),
+            2 => Some(Self::Kill),
+            _ => None,
+        }
+    }
+}
+
+/// A process's installed filter. [`super::inherit_seccomp`] clones it onto
+/// a child's `ProcessControlBlock`; nothing calls that on `fork`/thread
+/// creation, so a child starts with `SeccompFilter::default()`.
+#[derive(Clone)]
+pub struct SeccompFilter {
+    pub mode: SeccompMode,
+    allow: Vec<u8>,
+}
+
+impl Default for SeccompFilter {
+    /// No filter: every syscall id allowed.
+    fn default() -> Self {
+        Self {
+            mode: SeccompMode::Off,
+            allow: vec![0xff; BITMAP_LEN_BYTES],
+        }
+    }
+}
+
+impl SeccompFilter {
+    /// Install a new filter from a `mode` and a raw allow bitmap (one bit
+    /// per syscall id, copied from user memory by the caller).
+    pub fn install(mode: SeccompMode, allow_bitmap: &[u8]) -> Self {
+        let mut allow = vec![0u8; BITMAP_LEN_BYTES];
+        let n = allow.len().min(allow_bitmap.len());
+        allow[..n].copy_from_slice(&allow_bitmap[..n]);
+        Self { mode, allow }
+    }
+
+    /// Is `syscall_id` reachable under this filter?
+    pub fn is_allowed(&self, syscall_id: usize) -> bool {
+        match self.mode {
+            SeccompMode::Off => true,
+            _ => {
+                let byte = syscall_id / 8;
+                let bit = syscall_id % 8;
+                byte < self.allow.len() && (self.allow[byte] >> bit) & 1 != 0
+            }
+        }
+    }
+}
+
+/// What the trap/syscall dispatcher should do about a given syscall id,
+/// after consulting the current process's [`SeccompFilter`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SeccompVerdict {
+    /// Dispatch the syscall normally.
+    Allowed,
+    /// Skip dispatch and return `-EPERM` to the caller.
+    DeniedErrno,
+    /// Skip dispatch and kill the calling task.
+    DeniedKill,
+}
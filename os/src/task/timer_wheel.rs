@@ -0,0 +1,116 @@
+//! Kernel timer wheel backing deadline-aware blocking (`Semaphore::down_timeout`,
+//! `Condvar::wait_timeout`).
+//!
+//! Every entry races a deadline against a concurrent `up()`/`signal()`:
+//! firing the timer attempts to remove the waiter from its own wait queue,
+//! and only wakes it (flagged as a timeout) if that removal actually
+//! happens. If `up()`/`signal()` already popped the waiter, the removal
+//! finds nothing and the entry is silently dropped.
+
+use super::{wakeup_task, TaskControlBlock};
+use crate::sync::SpinLock;
+use alloc::boxed::Box;
+use alloc::collections::BinaryHeap;
+use alloc::sync::Arc;
+use core::cmp::Ordering;
+use lazy_static::*;
+
+struct TimerEntry {
+    expire: usize,
+    task: Arc<TaskControlBlock>,
+    /// Tries to remove this waiter from the wait queue it registered with;
+    /// `true` means the timer won the race and should wake it.
+    remove_from_wait_queue: Box<dyn FnOnce() -> bool + Send>,
+}
+
+impl PartialEq for TimerEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.expire == other.expire
+    }
+}
+impl Eq for TimerEntry {}
+impl PartialOrd for TimerEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for TimerEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `BinaryHeap` is a max-heap; reverse so the earliest deadline sorts first.
+        other.expire.cmp(&self.expire)
+    }
+}
+
+/// A heap of `(expire_tick, Arc<TaskControlBlock>)` entries, earliest
+/// deadline first, consulted by [`check_timers`].
+pub struct TimerWheel {
+    entries: BinaryHeap<TimerEntry>,
+}
+
+impl TimerWheel {
+    pub fn new() -> Self {
+        Self {
+            entries: BinaryHeap::new(),
+        }
+    }
+
+    fn add(
+        &mut self,
+        expire: usize,
+        task: Arc<TaskControlBlock>,
+        remove_from_wait_queue: Box<dyn FnOnce() -> bool + Send>,
+    ) {
+        self.entries.push(TimerEntry {
+            expire,
+            task,
+            remove_from_wait_queue,
+        });
+    }
+
+    /// Fire every entry whose deadline has elapsed by `now`.
+    fn check(&mut self, now: usize) {
+        while matches!(self.entries.peek(), Some(e) if e.expire <= now) {
+            let entry = self.entries.pop().unwrap();
+            if (entry.remove_from_wait_queue)() {
+                entry.task.inner_exclusive_access().woken_by_timeout = true;
+                wakeup_task(entry.task);
+            }
+        }
+    }
+}
+
+lazy_static! {
+    /// TIMER_WHEEL instance through lazy_static!
+    ///
+    /// Checked from every hart's `run_tasks` loop, so this is a real
+    /// `SpinLock` rather than a `UPSafeCell`.
+    pub static ref TIMER_WHEEL: SpinLock<TimerWheel> = SpinLock::new(TimerWheel::new());
+}
+
+/// Register a deadline for `task`; `remove_from_wait_queue` is called once
+/// the deadline elapses, and the task is woken only if it returns `true`
+/// (the waiter was still queued, so nothing else woke it first).
+pub fn add_timer(
+    expire: usize,
+    task: Arc<TaskControlBlock>,
+    remove_from_wait_queue: Box<dyn FnOnce() -> bool + Send>,
+) {
+    TIMER_WHEEL
+        .exclusive_access()
+        .add(expire, task, remove_from_wait_queue);
+}
+
+/// Drive the timer wheel forward.
+///
+/// Polled from every hart's `run_tasks` idle loop (`task/processor.rs`), so
+/// deadlines fire even though this tree has no `trap/mod.rs` to drive it
+/// from the `SupervisorTimer` interrupt directly. That's coarser than a
+/// real timer interrupt — a hart stuck running a task between `run_tasks`
+/// iterations delays every deadline it would have fired — but it beats
+/// `down_timeout`/`wait_timeout` never firing at all. Once `trap/mod.rs`
+/// exists, the `Trap::Interrupt(Interrupt::SupervisorTimer)` arm should
+/// call `check_timers(get_time())` directly alongside its
+/// `set_next_trigger()` call, and this call site can move there.
+pub fn check_timers(now: usize) {
+    TIMER_WHEEL.exclusive_access().check(now);
+}